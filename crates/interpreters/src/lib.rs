@@ -1,17 +1,49 @@
 use anyhow::{bail, Context, Result};
+use proc_macro2::Span;
+use quote::ToTokens;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Display;
 use syn::{
-  Block, Expr, ExprAssign, ExprLit, ExprPath, ExprReference, ExprUnary, Ident, Lit, LitInt, Local,
-  Pat, PatIdent, Stmt, UnOp,
+  Block, Expr, ExprAssign, ExprIf, ExprLet, ExprLit, ExprMatch, ExprPath, ExprReference,
+  ExprUnary, Ident, Lit, Local, Pat, PatIdent, PatLit, Stmt, UnOp,
 };
 
-pub type Place = Ident;
+/// A binding's unique identity: its surface name plus a monotonic occurrence
+/// counter minted by [`Environment::insert`]. Shadowing (`let a = 1; let a =
+/// &a;`) gives two bindings the same name, but they never compare equal here
+/// -- so a `Value::Ref` taken against the first `a` still targets it, even
+/// after the second `a` shadows it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Place {
+  name: Ident,
+  occurrence: u64,
+}
+
+impl Place {
+  /// A place with no real binding behind it, standing in for "the program as
+  /// a whole" when [`diverge`] needs to report a disagreement that isn't
+  /// about any one variable (e.g. one model erroring outright).
+  fn synthetic(name: &str) -> Place {
+    Place {
+      name: Ident::new(name, Span::call_site()),
+      occurrence: 0,
+    }
+  }
+}
+
+impl Display for Place {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name)
+  }
+}
+
 #[derive(Debug, Clone)]
 pub enum Value {
   Unit,
   Lit(Lit),
   Ref(Place),
+  RefMut(Place),
   Undefined,
 }
 
@@ -21,22 +53,62 @@ impl Display for Value {
       Value::Unit => write!(f, "()")?,
       Value::Lit(l) => match l {
         Lit::Int(i) => write!(f, "{}", i.token())?,
+        Lit::Bool(b) => write!(f, "{}", b.value)?,
         _ => todo!("{l:?}"),
       },
       Value::Ref(p) => write!(f, "&{}", p)?,
+      Value::RefMut(p) => write!(f, "&mut {}", p)?,
       Value::Undefined => write!(f, "undefined")?,
     }
     Ok(())
   }
 }
 
+// A scope is an ordered list of bindings rather than a map, so that shadowing
+// within a single block (`let a = 1; let a = 2;`) keeps both occurrences instead
+// of the later one clobbering the earlier one in place.
+type Scope = Vec<(Place, Value)>;
+
 #[derive(Default, Debug)]
-pub struct Environment(HashMap<Place, Value>);
+pub struct Environment {
+  scopes: Vec<Scope>,
+  /// Monotonic counter minted into every [`Place`] by `insert`, so that
+  /// shadowed bindings of the same name still get distinct identities.
+  next_occurrence: u64,
+}
 impl Environment {
-  pub fn lookup(&self, place: &Place) -> Result<&Value> {
+  /// Resolve a surface name to the identity of its innermost live binding --
+  /// the one a read of that name refers to from this point in the program.
+  /// The AST only ever gives us a name (`Expr::Path`, `&a`), never a
+  /// [`Place`] directly, so this is how every name-based lookup starts.
+  fn resolve(&self, name: &Ident) -> Result<Place> {
+    self
+      .scopes
+      .iter()
+      .rev()
+      .find_map(|scope| {
+        scope
+          .iter()
+          .rev()
+          .find(|(place, _)| &place.name == name)
+          .map(|(place, _)| place.clone())
+      })
+      .with_context(|| format!("Cannot find place: {name:?}"))
+  }
+
+  pub fn lookup(&self, name: &Ident) -> Result<&Value> {
+    let place = self.resolve(name)?;
+    self.lookup_place(&place)
+  }
+
+  /// Look up a binding by its already-resolved identity, e.g. the target of
+  /// a `Value::Ref`/`RefMut` or the place an assignment ultimately writes to.
+  fn lookup_place(&self, place: &Place) -> Result<&Value> {
     let value = self
-      .0
-      .get(place)
+      .scopes
+      .iter()
+      .rev()
+      .find_map(|scope| scope.iter().rev().find(|(p, _)| p == place).map(|(_, v)| v))
       .with_context(|| format!("Cannot find place: {place:?}"))?;
     match value {
       Value::Undefined => bail!("Attempting to read undefined place: {place:?}"),
@@ -44,47 +116,215 @@ impl Environment {
     }
   }
 
-  pub fn insert(&mut self, place: Place, value: Value) {
-    self.0.insert(place, value);
+  /// Introduce a new (possibly shadowing) binding named `name` in the
+  /// current scope, as `let` does, minting a fresh [`Place`] identity for it
+  /// regardless of whether `name` shadows an existing binding.
+  pub fn insert(&mut self, name: Ident, value: Value) -> Place {
+    let place = Place {
+      name,
+      occurrence: self.next_occurrence,
+    };
+    self.next_occurrence += 1;
+    self
+      .scopes
+      .last_mut()
+      .expect("Environment has no open scope; call push_scope first")
+      .push((place.clone(), value));
+    place
+  }
+
+  /// Overwrite the nearest existing binding for `place` in place, as plain
+  /// assignment (`place = value`) does. Unlike `insert`, this must not push a
+  /// fresh binding onto the current scope: an assignment to a variable
+  /// declared in an outer scope (e.g. from inside an `if` branch) needs to
+  /// mutate that outer binding so the new value is still visible once the
+  /// inner scope is popped.
+  pub fn assign(&mut self, place: &Place, value: Value) -> Result<()> {
+    self
+      .scopes
+      .iter_mut()
+      .rev()
+      .find_map(|scope| scope.iter_mut().rev().find(|(p, _)| p == place))
+      .with_context(|| format!("Cannot find place: {place:?}"))?
+      .1 = value;
+    Ok(())
+  }
+
+  pub fn push_scope(&mut self) {
+    self.scopes.push(Vec::new());
+  }
+
+  pub fn pop_scope(&mut self) {
+    self.scopes.pop().expect("pop_scope with no open scope");
+  }
+
+  /// All places bound anywhere in the store, deduplicated and sorted. Used by
+  /// [`diverge`] to walk two environments in lockstep regardless of which
+  /// scope frame each place happens to live in.
+  fn places(&self) -> Vec<Place> {
+    let mut places = self
+      .scopes
+      .iter()
+      .flat_map(|scope| scope.iter().map(|(p, _)| p.clone()))
+      .collect::<Vec<_>>();
+    places.sort();
+    places.dedup();
+    places
+  }
+
+  /// Every binding in definition order: outermost frame first, and within a
+  /// frame, in the order `insert` was called.
+  fn bindings_in_definition_order(&self) -> Vec<(&Place, &Value)> {
+    self
+      .scopes
+      .iter()
+      .flat_map(|scope| scope.iter().map(|(p, v)| (p, v)))
+      .collect()
+  }
+
+  /// Canonicalize this store by renaming each binding to the De Bruijn-style
+  /// index of its position in definition order, and each `Ref`/`RefMut`
+  /// payload to the index of the binding it targets -- resolved the same way
+  /// `lookup` resolves a place, i.e. the most recent matching binding. Used by
+  /// [`alpha_eq`](Environment::alpha_eq).
+  fn canonicalize(&self) -> Vec<CanonicalValue> {
+    let bindings = self.bindings_in_definition_order();
+    let target_index = |place: &Place| bindings.iter().rposition(|(p, _)| *p == place);
+    bindings
+      .iter()
+      .map(|(_, value)| match value {
+        Value::Unit => CanonicalValue::Unit,
+        Value::Lit(lit) => CanonicalValue::Lit(Value::Lit(lit.clone()).to_string()),
+        Value::Ref(target) => CanonicalValue::Ref(target_index(target)),
+        Value::RefMut(target) => CanonicalValue::RefMut(target_index(target)),
+        Value::Undefined => CanonicalValue::Undefined,
+      })
+      .collect()
+  }
+
+  /// Compare two stores up to consistent renaming of places, following the
+  /// Shift/Subst/alpha-variable machinery Dhall uses for its values: two
+  /// environments with the same binding shape and aliasing graph are equal
+  /// even if their places have different names, e.g. `{ let a = 1; let b =
+  /// &a; }` and `{ let x = 1; let y = &x; }`. The renaming is applied
+  /// transitively through `Ref`/`RefMut` payloads, so two environments whose
+  /// places alias differently compare unequal even with identical names.
+  pub fn alpha_eq(&self, other: &Environment) -> bool {
+    self.canonicalize() == other.canonicalize()
   }
 }
 
+/// The canonicalized shape of a single binding, as produced by
+/// [`Environment::canonicalize`]: places are erased in favor of their
+/// position in definition order.
+#[derive(Debug, PartialEq)]
+enum CanonicalValue {
+  Unit,
+  Lit(String),
+  Ref(Option<usize>),
+  RefMut(Option<usize>),
+  Undefined,
+}
+
 impl Display for Environment {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    let mut entries = self.0.iter().collect::<Vec<_>>();
+    let mut entries = self
+      .scopes
+      .iter()
+      .flat_map(|scope| scope.iter())
+      .collect::<Vec<_>>();
     entries.sort_by_key(|(k, _)| k.clone());
     for (k, v) in entries {
-      write!(f, "{} â†¦ {}\n", k.to_string(), v)?;
+      writeln!(f, "{k} â†¦ {v}")?;
     }
 
     Ok(())
   }
 }
 
+/// Interpret a condition's `Value` as a boolean, the way `if`/`match` guards
+/// need to. Both integer and boolean literals are accepted, since the
+/// surface language doesn't distinguish them at this level of modeling.
+fn literal_truthy(value: &Value) -> Result<bool> {
+  match value {
+    Value::Lit(Lit::Bool(b)) => Ok(b.value),
+    Value::Lit(Lit::Int(i)) => Ok(i.base10_parse::<i64>()? != 0),
+    v => bail!("Expected a boolean or integer condition, found: {v:?}"),
+  }
+}
+
+/// Try to match `value` against `pat`, shared by both `match` arms and
+/// `if let`. Returns the bindings the pattern introduces on a match, or
+/// `None` if the pattern doesn't apply, so callers can fall through to the
+/// next arm (or the `else` branch).
+fn match_pattern(pat: &Pat, value: &Value) -> Result<Option<Vec<(Ident, Value)>>> {
+  Ok(match pat {
+    Pat::Wild(_) => Some(Vec::new()),
+    Pat::Ident(PatIdent {
+      ident, subpat: None, ..
+    }) => Some(vec![(ident.clone(), value.clone())]),
+    Pat::Lit(PatLit { expr, .. }) => match &**expr {
+      Expr::Lit(ExprLit { lit, .. }) => {
+        if Value::Lit(lit.clone()).to_string() == value.to_string() {
+          Some(Vec::new())
+        } else {
+          None
+        }
+      }
+      e => unimplemented!("{e:#?}"),
+    },
+    p => unimplemented!("{p:#?}"),
+  })
+}
+
 pub trait Interpreter {
   fn eval_block(&self, block: &Block, env: &mut Environment) -> Result<()>;
 
   fn interpret(&self, code: &str) -> Result<Environment> {
     let block: Block = syn::parse_str(code)?;
     let mut env = Environment::default();
+    env.push_scope();
     self.eval_block(&block, &mut env)?;
     Ok(env)
   }
+
+  /// Run `code` small-step, recording a snapshot of the environment after
+  /// each top-level statement alongside that statement's pretty-printed
+  /// text, the way a normalization phase can be driven one reduction at a
+  /// time. Built on [`eval_block`](Interpreter::eval_block) by re-running it
+  /// over successively longer statement prefixes, rather than plumbing a new
+  /// stepping primitive through every model.
+  fn trace(&self, code: &str) -> Result<Vec<(String, Environment)>> {
+    let block: Block = syn::parse_str(code)?;
+    let mut steps = Vec::with_capacity(block.stmts.len());
+    for i in 1..=block.stmts.len() {
+      let prefix = Block {
+        brace_token: block.brace_token,
+        stmts: block.stmts[..i].to_vec(),
+      };
+      let mut env = Environment::default();
+      env.push_scope();
+      self.eval_block(&prefix, &mut env)?;
+      let text = block.stmts[i - 1].to_token_stream().to_string();
+      steps.push((text, env));
+    }
+    Ok(steps)
+  }
 }
 
 pub struct ReferenceModel;
 impl ReferenceModel {
   fn eval_place(&self, expr: &Expr, env: &Environment) -> Result<Place> {
     Ok(match expr {
-      Expr::Path(ExprPath { path, .. }) => path.get_ident().unwrap().clone(),
+      Expr::Path(ExprPath { path, .. }) => env.resolve(path.get_ident().unwrap())?,
       Expr::Unary(ExprUnary {
         op: UnOp::Deref(..),
         expr,
         ..
       }) => {
         let place = self.eval_place(expr, env)?;
-        match env.lookup(&place)? {
-          Value::Ref(place) => place.clone(),
+        match env.lookup_place(&place)? {
+          Value::Ref(place) | Value::RefMut(place) => place.clone(),
           v => bail!("Cannot deref value: {v:?}"),
         }
       }
@@ -92,39 +332,152 @@ impl ReferenceModel {
     })
   }
 
+  /// Resolve the place an assignment's left-hand side ultimately writes to,
+  /// as in `eval_place`, but additionally require every dereference along
+  /// the way to pass through a `&mut` reference (mirroring edlang's
+  /// `AssignStmt::deref_times`): assigning through a shared `&` is rejected
+  /// even if a `&mut` appears further down the chain, since you can't regain
+  /// mutability once you've gone through a shared reference.
+  fn eval_assign_place(&self, expr: &Expr, env: &Environment) -> Result<Place> {
+    let mut deref_times = 0;
+    let mut base = expr;
+    while let Expr::Unary(ExprUnary {
+      op: UnOp::Deref(..),
+      expr,
+      ..
+    }) = base
+    {
+      deref_times += 1;
+      base = expr;
+    }
+
+    let mut place = self.eval_place(base, env)?;
+    for _ in 0..deref_times {
+      place = match env.lookup_place(&place)? {
+        Value::RefMut(referent) => referent.clone(),
+        Value::Ref(_) => bail!("Cannot assign through a shared reference: {place:?}"),
+        v => bail!("Cannot deref value: {v:?}"),
+      };
+    }
+    Ok(place)
+  }
+
   fn eval_expr(&self, expr: &Expr, env: &mut Environment) -> Result<Value> {
     Ok(match expr {
       Expr::Lit(ExprLit { lit, .. }) => Value::Lit(lit.clone()),
       Expr::Path(ExprPath { path, .. }) => env.lookup(path.get_ident().unwrap())?.clone(),
-      Expr::Reference(ExprReference { expr: inner, .. }) => match &**inner {
-        Expr::Path(ExprPath { path, .. }) => Value::Ref(path.get_ident().unwrap().clone()),
+      Expr::Reference(ExprReference {
+        expr: inner,
+        mutability,
+        ..
+      }) => match &**inner {
+        Expr::Path(ExprPath { path, .. }) => {
+          let place = env.resolve(path.get_ident().unwrap())?;
+          match mutability {
+            Some(_) => Value::RefMut(place),
+            None => Value::Ref(place),
+          }
+        }
         e => unimplemented!("{e:#?}"),
       },
       Expr::Unary(ExprUnary {
         op: UnOp::Deref(_), ..
       }) => {
         let place = self.eval_place(expr, env)?;
-        env.lookup(&place)?.clone()
+        env.lookup_place(&place)?.clone()
       }
       Expr::Assign(ExprAssign { left, right, .. }) => {
-        let l = self.eval_place(left, env)?;
+        let l = self.eval_assign_place(left, env)?;
         let r = self.eval_expr(right, env)?;
-        env.insert(l, r);
+        env.assign(&l, r)?;
         Value::Unit
       }
+      Expr::Block(expr_block) => {
+        env.push_scope();
+        let result = self.eval_block(&expr_block.block, env);
+        env.pop_scope();
+        result?;
+        Value::Unit
+      }
+      Expr::If(ExprIf {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      }) => {
+        env.push_scope();
+        let result = match &**cond {
+          Expr::Let(ExprLet { pat, expr, .. }) => {
+            let scrutinee = self.eval_expr(expr, env)?;
+            match match_pattern(pat, &scrutinee)? {
+              Some(bindings) => {
+                bindings.into_iter().for_each(|(name, v)| {
+                  env.insert(name, v);
+                });
+                self.eval_block(then_branch, env)
+              }
+              None => self.eval_else(else_branch, env),
+            }
+          }
+          cond => {
+            if literal_truthy(&self.eval_expr(cond, env)?)? {
+              self.eval_block(then_branch, env)
+            } else {
+              self.eval_else(else_branch, env)
+            }
+          }
+        };
+        env.pop_scope();
+        result?;
+        Value::Unit
+      }
+      Expr::Match(ExprMatch {
+        expr: scrutinee,
+        arms,
+        ..
+      }) => {
+        let value = self.eval_expr(scrutinee, env)?;
+        let mut taken = None;
+        for arm in arms {
+          if let Some(bindings) = match_pattern(&arm.pat, &value)? {
+            env.push_scope();
+            bindings.into_iter().for_each(|(name, v)| {
+              env.insert(name, v);
+            });
+            let result = self.eval_expr(&arm.body, env);
+            env.pop_scope();
+            taken = Some(result?);
+            break;
+          }
+        }
+        taken.context("Non-exhaustive match")?
+      }
       e => unimplemented!("{e:#?}"),
     })
   }
 
+  /// Evaluate an `if`/`if let`'s (possibly absent) `else` branch, which is
+  /// itself an `Expr` -- either a `Block` or a chained `Expr::If`.
+  fn eval_else(
+    &self,
+    else_branch: &Option<(syn::token::Else, Box<Expr>)>,
+    env: &mut Environment,
+  ) -> Result<()> {
+    match else_branch {
+      Some((_, else_expr)) => self.eval_expr(else_expr, env).map(|_| ()),
+      None => Ok(()),
+    }
+  }
+
   fn eval_stmt(&self, stmt: &Stmt, env: &mut Environment) -> Result<()> {
-    Ok(match stmt {
+    match stmt {
       Stmt::Local(Local { pat, init, .. }) => {
         let lhs = match pat {
           Pat::Ident(PatIdent { ident, .. }) => ident,
           _ => unimplemented!(),
         };
         let v = match init.as_ref() {
-          Some((_, rhs)) => self.eval_expr(&*rhs, env)?,
+          Some((_, rhs)) => self.eval_expr(rhs, env)?,
           None => Value::Undefined,
         };
         env.insert(lhs.clone(), v);
@@ -132,8 +485,12 @@ impl ReferenceModel {
       Stmt::Semi(expr, _) => {
         self.eval_expr(expr, env)?;
       }
+      Stmt::Expr(expr) => {
+        self.eval_expr(expr, env)?;
+      }
       s => unimplemented!("{s:#?}"),
-    })
+    }
+    Ok(())
   }
 }
 
@@ -146,6 +503,731 @@ impl Interpreter for ReferenceModel {
   }
 }
 
+/// A second aliasing discipline, in the spirit of Stacked Borrows: every place
+/// carries a monotonically increasing generation tag that advances each time
+/// the place is written, and every `&`-reference remembers the generation of
+/// its referent at the moment it was taken. Dereferencing a reference whose
+/// remembered generation no longer matches the referent's current generation
+/// means the referent was reassigned out from under it, so the deref is
+/// rejected instead of silently reading the new value.
+#[derive(Default)]
+pub struct StackedBorrowsModel {
+  next_tag: RefCell<u64>,
+  place_tag: RefCell<HashMap<Place, u64>>,
+  ref_tag: RefCell<HashMap<Place, u64>>,
+}
+
+impl StackedBorrowsModel {
+  fn bump_tag(&self, place: &Place) -> u64 {
+    let mut next_tag = self.next_tag.borrow_mut();
+    *next_tag += 1;
+    self.place_tag.borrow_mut().insert(place.clone(), *next_tag);
+    *next_tag
+  }
+
+  fn current_tag(&self, place: &Place) -> u64 {
+    self.place_tag.borrow().get(place).copied().unwrap_or(0)
+  }
+
+  /// Record that `place` now holds a reference to `referent`'s current
+  /// generation, so a later deref through `place` can detect whether
+  /// `referent` has since been reassigned.
+  fn track_reference(&self, place: &Place, value: &Value) {
+    let referent = match value {
+      Value::Ref(referent) | Value::RefMut(referent) => referent,
+      _ => return,
+    };
+    self
+      .ref_tag
+      .borrow_mut()
+      .insert(place.clone(), self.current_tag(referent));
+  }
+
+  fn eval_place(&self, expr: &Expr, env: &Environment) -> Result<Place> {
+    Ok(match expr {
+      Expr::Path(ExprPath { path, .. }) => env.resolve(path.get_ident().unwrap())?,
+      Expr::Unary(ExprUnary {
+        op: UnOp::Deref(..),
+        expr,
+        ..
+      }) => {
+        let ref_place = self.eval_place(expr, env)?;
+        match env.lookup_place(&ref_place)? {
+          Value::Ref(referent) | Value::RefMut(referent) => {
+            let captured = self.ref_tag.borrow().get(&ref_place).copied().unwrap_or(0);
+            if captured != self.current_tag(referent) {
+              bail!("Reference in `{ref_place}` was invalidated by a write to `{referent}`");
+            }
+            referent.clone()
+          }
+          v => bail!("Cannot deref value: {v:?}"),
+        }
+      }
+      _ => unimplemented!("{expr:#?}"),
+    })
+  }
+
+  /// Same idea as `ReferenceModel::eval_assign_place`: walk an arbitrary-depth
+  /// deref chain on an assignment's left-hand side, requiring every hop to
+  /// pass through a `&mut` reference whose borrow tag is still valid. Also
+  /// returns the last hop's reference place (e.g. `b` in `*b = ...`), if any,
+  /// so the caller can refresh its captured tag after the write -- the write
+  /// it just performed through that reference shouldn't invalidate itself.
+  fn eval_assign_place(&self, expr: &Expr, env: &Environment) -> Result<(Place, Option<Place>)> {
+    let mut deref_times = 0;
+    let mut base = expr;
+    while let Expr::Unary(ExprUnary {
+      op: UnOp::Deref(..),
+      expr,
+      ..
+    }) = base
+    {
+      deref_times += 1;
+      base = expr;
+    }
+
+    let mut place = self.eval_place(base, env)?;
+    let mut via = None;
+    for _ in 0..deref_times {
+      let referent = match env.lookup_place(&place)? {
+        Value::RefMut(referent) => referent.clone(),
+        Value::Ref(_) => bail!("Cannot assign through a shared reference: {place:?}"),
+        v => bail!("Cannot deref value: {v:?}"),
+      };
+      let captured = self.ref_tag.borrow().get(&place).copied().unwrap_or(0);
+      if captured != self.current_tag(&referent) {
+        bail!("Reference in `{place}` was invalidated by a write to `{referent}`");
+      }
+      via = Some(place);
+      place = referent;
+    }
+    Ok((place, via))
+  }
+
+  fn eval_expr(&self, expr: &Expr, env: &mut Environment) -> Result<Value> {
+    Ok(match expr {
+      Expr::Lit(ExprLit { lit, .. }) => Value::Lit(lit.clone()),
+      Expr::Path(ExprPath { path, .. }) => env.lookup(path.get_ident().unwrap())?.clone(),
+      Expr::Reference(ExprReference {
+        expr: inner,
+        mutability,
+        ..
+      }) => match &**inner {
+        Expr::Path(ExprPath { path, .. }) => {
+          let place = env.resolve(path.get_ident().unwrap())?;
+          match mutability {
+            Some(_) => Value::RefMut(place),
+            None => Value::Ref(place),
+          }
+        }
+        e => unimplemented!("{e:#?}"),
+      },
+      Expr::Unary(ExprUnary {
+        op: UnOp::Deref(_), ..
+      }) => {
+        let place = self.eval_place(expr, env)?;
+        env.lookup_place(&place)?.clone()
+      }
+      Expr::Assign(ExprAssign { left, right, .. }) => {
+        let (l, via) = self.eval_assign_place(left, env)?;
+        let r = self.eval_expr(right, env)?;
+        let new_tag = self.bump_tag(&l);
+        if let Some(via) = via {
+          // The write was performed through `via`, not behind its back, so
+          // `via` should stay valid for subsequent reads -- refresh its
+          // captured tag rather than letting its own write invalidate it.
+          self.ref_tag.borrow_mut().insert(via, new_tag);
+        }
+        self.track_reference(&l, &r);
+        env.assign(&l, r)?;
+        Value::Unit
+      }
+      Expr::Block(expr_block) => {
+        env.push_scope();
+        let result = self.eval_block(&expr_block.block, env);
+        env.pop_scope();
+        result?;
+        Value::Unit
+      }
+      Expr::If(ExprIf {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      }) => {
+        env.push_scope();
+        let result = match &**cond {
+          Expr::Let(ExprLet { pat, expr, .. }) => {
+            let scrutinee = self.eval_expr(expr, env)?;
+            match match_pattern(pat, &scrutinee)? {
+              Some(bindings) => {
+                bindings.into_iter().for_each(|(name, v)| {
+                  env.insert(name, v);
+                });
+                self.eval_block(then_branch, env)
+              }
+              None => self.eval_else(else_branch, env),
+            }
+          }
+          cond => {
+            if literal_truthy(&self.eval_expr(cond, env)?)? {
+              self.eval_block(then_branch, env)
+            } else {
+              self.eval_else(else_branch, env)
+            }
+          }
+        };
+        env.pop_scope();
+        result?;
+        Value::Unit
+      }
+      Expr::Match(ExprMatch {
+        expr: scrutinee,
+        arms,
+        ..
+      }) => {
+        let value = self.eval_expr(scrutinee, env)?;
+        let mut taken = None;
+        for arm in arms {
+          if let Some(bindings) = match_pattern(&arm.pat, &value)? {
+            env.push_scope();
+            bindings.into_iter().for_each(|(name, v)| {
+              env.insert(name, v);
+            });
+            let result = self.eval_expr(&arm.body, env);
+            env.pop_scope();
+            taken = Some(result?);
+            break;
+          }
+        }
+        taken.context("Non-exhaustive match")?
+      }
+      e => unimplemented!("{e:#?}"),
+    })
+  }
+
+  /// Evaluate an `if`/`if let`'s (possibly absent) `else` branch, which is
+  /// itself an `Expr` -- either a `Block` or a chained `Expr::If`.
+  fn eval_else(
+    &self,
+    else_branch: &Option<(syn::token::Else, Box<Expr>)>,
+    env: &mut Environment,
+  ) -> Result<()> {
+    match else_branch {
+      Some((_, else_expr)) => self.eval_expr(else_expr, env).map(|_| ()),
+      None => Ok(()),
+    }
+  }
+
+  fn eval_stmt(&self, stmt: &Stmt, env: &mut Environment) -> Result<()> {
+    match stmt {
+      Stmt::Local(Local { pat, init, .. }) => {
+        let lhs = match pat {
+          Pat::Ident(PatIdent { ident, .. }) => ident,
+          _ => unimplemented!(),
+        };
+        let v = match init.as_ref() {
+          Some((_, rhs)) => self.eval_expr(rhs, env)?,
+          None => Value::Undefined,
+        };
+        let place = env.insert(lhs.clone(), v.clone());
+        self.bump_tag(&place);
+        self.track_reference(&place, &v);
+      }
+      Stmt::Semi(expr, _) => {
+        self.eval_expr(expr, env)?;
+      }
+      Stmt::Expr(expr) => {
+        self.eval_expr(expr, env)?;
+      }
+      s => unimplemented!("{s:#?}"),
+    }
+    Ok(())
+  }
+}
+
+impl Interpreter for StackedBorrowsModel {
+  fn eval_block(&self, block: &Block, env: &mut Environment) -> Result<()> {
+    for stmt in &block.stmts {
+      self.eval_stmt(stmt, env)?;
+    }
+    Ok(())
+  }
+}
+
+/// The outcome of comparing two [`Interpreter`] runs of the same program.
+#[derive(Debug)]
+pub enum Divergence {
+  /// Both models agreed on every place in the final store.
+  Agree(Environment),
+  /// The models disagreed: `place` holds `left` under the first model and
+  /// `right` under the second. A model that errored entirely (rather than
+  /// disagreeing on a specific place) is reported under the synthetic place
+  /// `_program`, with `Value::Undefined` as both `left` and `right`; the report
+  /// does not say which of the two models errored.
+  Disagree {
+    place: Place,
+    left: Value,
+    right: Value,
+  },
+}
+
+/// Run `code` under two different [`Interpreter`] models and report where
+/// their resulting stores first disagree, giving a testable oracle for where
+/// two operational models of references part ways.
+pub fn diverge(code: &str, a: &impl Interpreter, b: &impl Interpreter) -> Result<Divergence> {
+  let left = a.interpret(code);
+  let right = b.interpret(code);
+
+  let (left_env, right_env) = match (left, right) {
+    (Ok(left_env), Ok(right_env)) => (left_env, right_env),
+    (Err(_), Ok(_)) | (Ok(_), Err(_)) => {
+      return Ok(Divergence::Disagree {
+        place: Place::synthetic("_program"),
+        left: Value::Undefined,
+        right: Value::Undefined,
+      })
+    }
+    (Err(e), Err(_)) => return Err(e),
+  };
+
+  let mut places = left_env.places();
+  for place in right_env.places() {
+    if !places.contains(&place) {
+      places.push(place);
+    }
+  }
+  places.sort();
+
+  for place in places {
+    let left_value = left_env.lookup_place(&place);
+    let right_value = right_env.lookup_place(&place);
+    let disagree = match (&left_value, &right_value) {
+      (Ok(l), Ok(r)) => l.to_string() != r.to_string(),
+      (Err(_), Err(_)) => false,
+      _ => true,
+    };
+    if disagree {
+      return Ok(Divergence::Disagree {
+        place,
+        left: left_value.unwrap_or(&Value::Undefined).clone(),
+        right: right_value.unwrap_or(&Value::Undefined).clone(),
+      });
+    }
+  }
+
+  Ok(Divergence::Agree(left_env))
+}
+
+/// Per-place ownership/borrow status tracked by [`OwnershipChecker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BorrowState {
+  Owned,
+  Moved,
+  BorrowedShared(u32),
+  BorrowedMut,
+}
+
+/// One scope frame's worth of `OwnershipChecker::declared` entries: a
+/// binding's name, its current [`BorrowState`], and whether its value is
+/// `Copy` (see `OwnershipChecker::expr_is_copy`).
+type DeclaredScope = Vec<(Ident, BorrowState, bool)>;
+
+/// A static analysis pass, run independently of [`Interpreter::eval_block`],
+/// that checks a program obeys Rust's ownership discipline: a non-`Copy` place
+/// read by value cannot be read again afterwards, and a `&mut` borrow cannot coexist
+/// with any other borrow of the same place. This is the crate's analogue of
+/// Dhall's separate typecheck phase or rust-analyzer's inference pass --
+/// it walks the same AST the interpreters do, but never touches a `Value`.
+/// States live in scope frames that mirror `Environment`'s, so a borrow
+/// taken inside a block is released once that block's scope pops.
+#[derive(Default)]
+pub struct OwnershipChecker {
+  /// Keyed by the binding's surface name (unlike `Environment`, which keys
+  /// `Value::Ref`/`RefMut` payloads by unique [`Place`]): this pass never
+  /// constructs references that need to survive shadowing, it only tracks
+  /// whether a name is currently readable. The `bool` records whether the
+  /// place's value is `Copy` -- see `expr_is_copy`.
+  declared: Vec<DeclaredScope>,
+  /// Places borrowed within the current scope, so their borrow can be
+  /// released when the scope pops rather than outliving it.
+  borrowed: Vec<Vec<Ident>>,
+}
+
+impl OwnershipChecker {
+  fn push_scope(&mut self) {
+    self.declared.push(Vec::new());
+    self.borrowed.push(Vec::new());
+  }
+
+  fn pop_scope(&mut self) -> Result<()> {
+    let borrowed = self.borrowed.pop().expect("pop_scope with no open scope");
+    for place in borrowed {
+      match self.state(&place)? {
+        BorrowState::BorrowedMut | BorrowState::BorrowedShared(1) => {
+          self.set_state(&place, BorrowState::Owned)?
+        }
+        BorrowState::BorrowedShared(n) => self.set_state(&place, BorrowState::BorrowedShared(n - 1))?,
+        BorrowState::Owned | BorrowState::Moved => {}
+      }
+    }
+    self
+      .declared
+      .pop()
+      .expect("pop_scope with no open scope");
+    Ok(())
+  }
+
+  fn declare(&mut self, place: Ident, state: BorrowState, copy: bool) {
+    self
+      .declared
+      .last_mut()
+      .expect("OwnershipChecker has no open scope; call push_scope first")
+      .push((place, state, copy));
+  }
+
+  /// Pattern bindings (`match`/`if let` arms) are `Copy` exactly when the
+  /// scrutinee they are bound from is (see `expr_is_copy`).
+  fn declare_pattern(&mut self, pat: &Pat, copy: bool) {
+    match pat {
+      Pat::Wild(_) | Pat::Lit(_) => {}
+      Pat::Ident(PatIdent {
+        ident, subpat: None, ..
+      }) => self.declare(ident.clone(), BorrowState::Owned, copy),
+      p => unimplemented!("{p:#?}"),
+    }
+  }
+
+  fn state(&self, place: &Ident) -> Result<BorrowState> {
+    self
+      .declared
+      .iter()
+      .rev()
+      .find_map(|scope| {
+        scope
+          .iter()
+          .rev()
+          .find(|(p, ..)| p == place)
+          .map(|(_, s, _)| *s)
+      })
+      .with_context(|| format!("Cannot find place: {place:?}"))
+  }
+
+  fn is_copy(&self, place: &Ident) -> Result<bool> {
+    self
+      .declared
+      .iter()
+      .rev()
+      .find_map(|scope| {
+        scope
+          .iter()
+          .rev()
+          .find(|(p, ..)| p == place)
+          .map(|(_, _, c)| *c)
+      })
+      .with_context(|| format!("Cannot find place: {place:?}"))
+  }
+
+  fn set_state(&mut self, place: &Ident, state: BorrowState) -> Result<()> {
+    self
+      .declared
+      .iter_mut()
+      .rev()
+      .find_map(|scope| scope.iter_mut().rev().find(|(p, ..)| p == place))
+      .with_context(|| format!("Cannot find place: {place:?}"))?
+      .1 = state;
+    Ok(())
+  }
+
+  /// Check that reading `place` without consuming it (e.g. reading the
+  /// reference held in `place` to dereference it, which is itself a `Copy`
+  /// read) is legal.
+  fn check_read(&self, place: &Ident) -> Result<()> {
+    if let BorrowState::Moved = self.state(place)? {
+      bail!("Use of moved place: {place:?}");
+    }
+    Ok(())
+  }
+
+  /// Check that reading `place` by value is legal. A `Copy` place (see
+  /// `expr_is_copy`) is unaffected by the read, same as `check_read`; a
+  /// non-`Copy` place is moved out of and cannot be read again.
+  fn check_move(&mut self, place: &Ident) -> Result<()> {
+    if self.is_copy(place)? {
+      return self.check_read(place);
+    }
+    match self.state(place)? {
+      BorrowState::Moved => bail!("Use of moved place: {place:?}"),
+      BorrowState::BorrowedShared(_) => bail!("Cannot move out of a borrowed place: {place:?}"),
+      BorrowState::BorrowedMut => bail!("Cannot move out of a mutably borrowed place: {place:?}"),
+      BorrowState::Owned => self.set_state(place, BorrowState::Moved),
+    }
+  }
+
+  fn check_shared_borrow(&mut self, place: &Ident) -> Result<()> {
+    let next = match self.state(place)? {
+      BorrowState::Moved => bail!("Cannot borrow moved place: {place:?}"),
+      BorrowState::BorrowedMut => bail!("Cannot borrow already mutably-borrowed place: {place:?}"),
+      BorrowState::Owned => BorrowState::BorrowedShared(1),
+      BorrowState::BorrowedShared(n) => BorrowState::BorrowedShared(n + 1),
+    };
+    self.set_state(place, next)?;
+    self.borrowed.last_mut().unwrap().push(place.clone());
+    Ok(())
+  }
+
+  fn check_mut_borrow(&mut self, place: &Ident) -> Result<()> {
+    match self.state(place)? {
+      BorrowState::Moved => bail!("Cannot borrow moved place: {place:?}"),
+      BorrowState::BorrowedMut | BorrowState::BorrowedShared(_) => {
+        bail!("Cannot mutably borrow an already-borrowed place: {place:?}")
+      }
+      BorrowState::Owned => self.set_state(place, BorrowState::BorrowedMut)?,
+    }
+    self.borrowed.last_mut().unwrap().push(place.clone());
+    Ok(())
+  }
+
+  fn check_assign(&mut self, place: &Ident) -> Result<()> {
+    match self.state(place)? {
+      BorrowState::BorrowedShared(_) => bail!("Cannot assign to a borrowed place: {place:?}"),
+      BorrowState::BorrowedMut => bail!("Cannot assign to a mutably borrowed place: {place:?}"),
+      BorrowState::Moved | BorrowState::Owned => self.set_state(place, BorrowState::Owned),
+    }
+  }
+
+  /// Whether evaluating `expr` to initialize a new binding merely copies
+  /// (vs. moves) its value. The model has no real type system, so this is
+  /// approximated structurally: taking `&mut x` is the only move-only value
+  /// this toy language can produce (matching real Rust, where `&mut T` never
+  /// implements `Copy`); copying from another place inherits that place's
+  /// `Copy`-ness; everything else (literals, shared references) is `Copy`.
+  fn expr_is_copy(&self, expr: &Expr) -> Result<bool> {
+    Ok(match expr {
+      Expr::Reference(ExprReference { mutability, .. }) => mutability.is_none(),
+      Expr::Path(ExprPath { path, .. }) => self.is_copy(path.get_ident().unwrap())?,
+      _ => true,
+    })
+  }
+
+  fn check_expr(&mut self, expr: &Expr) -> Result<()> {
+    match expr {
+      Expr::Lit(_) => {}
+      Expr::Path(ExprPath { path, .. }) => self.check_move(path.get_ident().unwrap())?,
+      Expr::Reference(ExprReference {
+        expr: inner,
+        mutability,
+        ..
+      }) => match &**inner {
+        Expr::Path(ExprPath { path, .. }) => {
+          let place = path.get_ident().unwrap();
+          match mutability {
+            Some(_) => self.check_mut_borrow(place)?,
+            None => self.check_shared_borrow(place)?,
+          }
+        }
+        e => unimplemented!("{e:#?}"),
+      },
+      Expr::Unary(ExprUnary {
+        op: UnOp::Deref(_),
+        expr: inner,
+        ..
+      }) => {
+        let mut base = &**inner;
+        while let Expr::Unary(ExprUnary {
+          op: UnOp::Deref(_),
+          expr,
+          ..
+        }) = base
+        {
+          base = expr;
+        }
+        match base {
+          Expr::Path(ExprPath { path, .. }) => self.check_read(path.get_ident().unwrap())?,
+          e => unimplemented!("{e:#?}"),
+        }
+      }
+      Expr::Assign(ExprAssign { left, right, .. }) => {
+        self.check_expr(right)?;
+        let mut deref_times = 0;
+        let mut base = &**left;
+        while let Expr::Unary(ExprUnary {
+          op: UnOp::Deref(_),
+          expr,
+          ..
+        }) = base
+        {
+          deref_times += 1;
+          base = expr;
+        }
+        match base {
+          // A direct assignment (`x = ...`) reassigns `x` itself. An
+          // assignment through one or more derefs (`*b = ...`) instead
+          // reads `b` (a `Copy` reference read, not a move) and writes
+          // through it, so `b`'s own borrow state is untouched.
+          Expr::Path(ExprPath { path, .. }) if deref_times == 0 => {
+            self.check_assign(path.get_ident().unwrap())?
+          }
+          Expr::Path(ExprPath { path, .. }) => self.check_read(path.get_ident().unwrap())?,
+          e => unimplemented!("{e:#?}"),
+        }
+      }
+      Expr::Block(expr_block) => {
+        self.push_scope();
+        let result = self.check_block(&expr_block.block);
+        self.pop_scope()?;
+        result?;
+      }
+      Expr::If(ExprIf {
+        cond,
+        then_branch,
+        else_branch,
+        ..
+      }) => {
+        // The condition itself always runs, regardless of which branch is
+        // taken, so it's checked unconditionally before either branch's
+        // snapshot is taken -- only an `if let`'s pattern binding is
+        // branch-local.
+        let pat = match &**cond {
+          Expr::Let(ExprLet { pat, expr, .. }) => {
+            self.check_expr(expr)?;
+            Some((pat, self.expr_is_copy(expr)?))
+          }
+          cond => {
+            self.check_expr(cond)?;
+            None
+          }
+        };
+
+        // Check each branch against the same pre-branch snapshot, rather
+        // than letting one branch's effects leak into the next: only one of
+        // them actually runs, so e.g. `if c { let x = a; } else { let y =
+        // a; }` moving `a` in both arms independently is fine.
+        let snapshot = self.declared.clone();
+
+        self.push_scope();
+        if let Some((pat, copy)) = pat {
+          self.declare_pattern(pat, copy);
+        }
+        let then_result = self.check_block(then_branch);
+        self.pop_scope()?;
+        then_result?;
+        let then_declared = std::mem::replace(&mut self.declared, snapshot.clone());
+
+        self.push_scope();
+        let else_result = self.check_else(else_branch);
+        self.pop_scope()?;
+        else_result?;
+        let else_declared = std::mem::replace(&mut self.declared, snapshot.clone());
+
+        self.declared = merge_branch_states(&snapshot, &[then_declared, else_declared]);
+      }
+      Expr::Match(ExprMatch {
+        expr: scrutinee,
+        arms,
+        ..
+      }) => {
+        self.check_expr(scrutinee)?;
+        let copy = self.expr_is_copy(scrutinee)?;
+
+        let snapshot = self.declared.clone();
+        let mut arm_declared = Vec::with_capacity(arms.len());
+        for arm in arms {
+          self.declared = snapshot.clone();
+          self.push_scope();
+          self.declare_pattern(&arm.pat, copy);
+          let result = self.check_expr(&arm.body);
+          self.pop_scope()?;
+          result?;
+          arm_declared.push(std::mem::take(&mut self.declared));
+        }
+        self.declared = merge_branch_states(&snapshot, &arm_declared);
+      }
+      e => unimplemented!("{e:#?}"),
+    }
+    Ok(())
+  }
+
+  /// Check an `if`/`if let`'s (possibly absent) `else` branch.
+  fn check_else(&mut self, else_branch: &Option<(syn::token::Else, Box<Expr>)>) -> Result<()> {
+    match else_branch {
+      Some((_, else_expr)) => self.check_expr(else_expr),
+      None => Ok(()),
+    }
+  }
+
+  fn check_stmt(&mut self, stmt: &Stmt) -> Result<()> {
+    match stmt {
+      Stmt::Local(Local { pat, init, .. }) => {
+        let lhs = match pat {
+          Pat::Ident(PatIdent { ident, .. }) => ident,
+          _ => unimplemented!(),
+        };
+        let copy = match init {
+          Some((_, rhs)) => {
+            self.check_expr(rhs)?;
+            self.expr_is_copy(rhs)?
+          }
+          None => true,
+        };
+        self.declare(lhs.clone(), BorrowState::Owned, copy);
+      }
+      Stmt::Semi(expr, _) => self.check_expr(expr)?,
+      Stmt::Expr(expr) => self.check_expr(expr)?,
+      s => unimplemented!("{s:#?}"),
+    }
+    Ok(())
+  }
+
+  fn check_block(&mut self, block: &Block) -> Result<()> {
+    for stmt in &block.stmts {
+      self.check_stmt(stmt)?;
+    }
+    Ok(())
+  }
+}
+
+/// Merge the outer-binding states left by each of an `if`/`match`'s branches
+/// back into one: since only one branch actually runs, but which one isn't
+/// known statically, a place that's `Moved` on *any* path must be treated as
+/// moved after the branch -- the same reasoning rustc's borrow checker uses,
+/// since the unrealized paths could just as well be the one actually taken.
+fn merge_branch_states(
+  pre_branch: &[DeclaredScope],
+  branches: &[Vec<DeclaredScope>],
+) -> Vec<DeclaredScope> {
+  if branches.is_empty() {
+    return pre_branch.to_vec();
+  }
+  let mut merged = pre_branch.to_vec();
+  for (scope_idx, scope) in merged.iter_mut().enumerate() {
+    for (place, state, _) in scope.iter_mut() {
+      let moved_on_any_path = branches.iter().any(|branch| {
+        matches!(
+          branch[scope_idx].iter().find(|(p, ..)| p == place),
+          Some((_, BorrowState::Moved, _))
+        )
+      });
+      if moved_on_any_path {
+        *state = BorrowState::Moved;
+      }
+    }
+  }
+  merged
+}
+
+/// Check that `code` obeys Rust's ownership discipline -- no use-after-move,
+/// no assignment to a borrowed place, no `&mut` aliasing another borrow --
+/// independent of any [`Interpreter`]. This lets the crate distinguish
+/// programs the reference interpreter would happily run from ones that are
+/// actually well-borrowed.
+pub fn check_ownership(code: &str) -> Result<()> {
+  let block: Block = syn::parse_str(code)?;
+  let mut checker = OwnershipChecker::default();
+  checker.push_scope();
+  checker.check_block(&block)
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -161,9 +1243,227 @@ mod test {
 
     let model = ReferenceModel;
     let mut env = Environment::default();
+    env.push_scope();
     model.eval_block(&block, &mut env).unwrap();
     println!("{env}");
 
     Ok(())
   }
+
+  #[test]
+  fn reference_to_a_shadowed_binding_targets_the_original() -> Result<()> {
+    let model = ReferenceModel;
+    let env = model.interpret("{ let a = 1; let a = &a; let c = *a; }")?;
+    let c = env.lookup(&syn::parse_str("c")?)?;
+    assert_eq!(c.to_string(), "1");
+    Ok(())
+  }
+
+  #[test]
+  fn shadowing_and_nested_scope() -> Result<()> {
+    let model = ReferenceModel;
+    let env = model.interpret("{ let a = 1; { let a = 2; } let b = a; }")?;
+    println!("{env}");
+
+    let a = env.lookup(&syn::parse_str("a")?)?;
+    assert_eq!(a.to_string(), "1");
+
+    let b = env.lookup(&syn::parse_str("b")?)?;
+    assert_eq!(b.to_string(), "1");
+
+    Ok(())
+  }
+
+  #[test]
+  fn diverge_agrees_without_aliasing_conflict() -> Result<()> {
+    let divergence = diverge(
+      "{ let a = 1; let b = &a; let c = *b; }",
+      &ReferenceModel,
+      &StackedBorrowsModel::default(),
+    )?;
+    assert!(matches!(divergence, Divergence::Agree(_)));
+    Ok(())
+  }
+
+  #[test]
+  fn diverge_finds_reference_invalidated_by_reassignment() -> Result<()> {
+    let divergence = diverge(
+      "{ let a = 1; let b = &a; a = 2; let c = *b; }",
+      &ReferenceModel,
+      &StackedBorrowsModel::default(),
+    )?;
+    assert!(matches!(divergence, Divergence::Disagree { .. }));
+    Ok(())
+  }
+
+  #[test]
+  fn diverge_agrees_on_write_then_read_through_the_same_mut_reference() -> Result<()> {
+    let divergence = diverge(
+      "{ let mut a = 1; let b = &mut a; *b = 2; let c = *b; }",
+      &ReferenceModel,
+      &StackedBorrowsModel::default(),
+    )?;
+    assert!(matches!(divergence, Divergence::Agree(_)));
+    Ok(())
+  }
+
+  #[test]
+  fn mutable_deref_assign_through_multiple_levels() -> Result<()> {
+    let model = ReferenceModel;
+    let env =
+      model.interpret("{ let mut a = 1; let b = &mut a; let p = &mut b; **p = 2; let c = a; }")?;
+    println!("{env}");
+
+    let c = env.lookup(&syn::parse_str("c")?)?;
+    assert_eq!(c.to_string(), "2");
+
+    Ok(())
+  }
+
+  #[test]
+  fn assigning_through_shared_reference_is_rejected() {
+    let model = ReferenceModel;
+    let err = model
+      .interpret("{ let a = 1; let b = &a; *b = 2; }")
+      .unwrap_err();
+    assert!(err.to_string().contains("shared reference"));
+  }
+
+  #[test]
+  fn if_else_picks_the_taken_branch() -> Result<()> {
+    let model = ReferenceModel;
+    let env = model.interpret("{ let cond = 0; let a; if cond { a = 1; } else { a = 2; } }")?;
+    let a = env.lookup(&syn::parse_str("a")?)?;
+    assert_eq!(a.to_string(), "2");
+    Ok(())
+  }
+
+  #[test]
+  fn match_falls_through_to_wildcard() -> Result<()> {
+    let model = ReferenceModel;
+    let env = model.interpret("{ let x = 2; let a = match x { 1 => 10, _ => 20 }; }")?;
+    let a = env.lookup(&syn::parse_str("a")?)?;
+    assert_eq!(a.to_string(), "20");
+    Ok(())
+  }
+
+  #[test]
+  fn non_exhaustive_match_errors() {
+    let model = ReferenceModel;
+    let err = model
+      .interpret("{ let x = 2; let a = match x { 1 => 10 }; }")
+      .unwrap_err();
+    assert!(err.to_string().contains("Non-exhaustive match"));
+  }
+
+  #[test]
+  fn if_let_binds_irrefutable_pattern() -> Result<()> {
+    let model = ReferenceModel;
+    let env = model.interpret("{ let x = 1; let a; if let y = x { a = y; } }")?;
+    let a = env.lookup(&syn::parse_str("a")?)?;
+    assert_eq!(a.to_string(), "1");
+    Ok(())
+  }
+
+  #[test]
+  fn use_after_move_is_rejected() {
+    let err =
+      check_ownership("{ let mut a = 1; let b = &mut a; let c = b; let d = b; }").unwrap_err();
+    assert!(err.to_string().contains("moved"));
+  }
+
+  #[test]
+  fn copy_places_can_be_read_more_than_once() -> Result<()> {
+    check_ownership("{ let a = 1; let b = a; let c = a; }")
+  }
+
+  #[test]
+  fn multiple_shared_borrows_are_allowed() -> Result<()> {
+    check_ownership("{ let a = 1; let b = &a; let c = &a; }")
+  }
+
+  #[test]
+  fn moving_a_place_in_each_if_branch_independently_is_allowed() -> Result<()> {
+    check_ownership(
+      "{ let mut a = 1; let b = &mut a; let c = 0; \
+       if c { let x = b; } else { let y = b; } }",
+    )
+  }
+
+  #[test]
+  fn moving_a_place_in_each_match_arm_independently_is_allowed() -> Result<()> {
+    check_ownership("{ let mut a = 1; let b = &mut a; let x = 1; match x { 1 => b, _ => b } }")
+  }
+
+  #[test]
+  fn moving_a_place_on_every_branch_is_still_a_move_afterward() {
+    let err = check_ownership(
+      "{ let mut a = 1; let b = &mut a; let c = 0; \
+       if c { let x = b; } else { let y = b; } let z = b; }",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("moved"));
+  }
+
+  #[test]
+  fn moving_a_place_on_only_one_branch_is_still_a_move_afterward() {
+    let err = check_ownership(
+      "{ let mut a = 1; let b = &mut a; let c = 0; if c { let x = b; } let z = b; }",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("moved"));
+  }
+
+  #[test]
+  fn mutable_borrow_while_shared_borrowed_is_rejected() {
+    let err = check_ownership("{ let mut a = 1; let b = &a; let c = &mut a; }").unwrap_err();
+    assert!(err.to_string().contains("borrowed"));
+  }
+
+  #[test]
+  fn borrows_are_released_at_end_of_scope() -> Result<()> {
+    check_ownership("{ let mut a = 1; { let b = &mut a; } let c = &mut a; }")
+  }
+
+  #[test]
+  fn assigning_to_a_borrowed_place_is_rejected() {
+    let err = check_ownership("{ let mut a = 1; let b = &a; a = 2; }").unwrap_err();
+    assert!(err.to_string().contains("borrowed"));
+  }
+
+  #[test]
+  fn alpha_eq_ignores_place_names() -> Result<()> {
+    let model = ReferenceModel;
+    let left = model.interpret("{ let a = 1; let b = &a; }")?;
+    let right = model.interpret("{ let x = 1; let y = &x; }")?;
+    assert!(left.alpha_eq(&right));
+    Ok(())
+  }
+
+  #[test]
+  fn alpha_eq_rejects_different_aliasing_graphs() -> Result<()> {
+    let model = ReferenceModel;
+    let left = model.interpret("{ let a = 1; let b = 2; let c = &a; }")?;
+    let right = model.interpret("{ let a = 1; let b = 2; let c = &b; }")?;
+    assert!(!left.alpha_eq(&right));
+    Ok(())
+  }
+
+  #[test]
+  fn trace_snapshots_the_environment_after_each_statement() -> Result<()> {
+    let model = ReferenceModel;
+    let steps = model.trace("{ let a = 1; let b = 2; }")?;
+
+    assert_eq!(steps.len(), 2);
+    let (first_stmt, first_env) = &steps[0];
+    assert!(first_stmt.contains("let a"));
+    assert_eq!(first_env.lookup(&syn::parse_str("a")?)?.to_string(), "1");
+    assert!(first_env.lookup(&syn::parse_str("b")?).is_err());
+
+    let (second_stmt, second_env) = &steps[1];
+    assert!(second_stmt.contains("let b"));
+    assert_eq!(second_env.lookup(&syn::parse_str("b")?)?.to_string(), "2");
+
+    Ok(())
+  }
 }